@@ -1,6 +1,19 @@
 fn main() {
-    println!("Keyboard layouts");
+    println!("Keyboard models");
     let layouts = xkb_data::keyboard_layouts().unwrap();
+    for model in layouts.models() {
+        println!("  {}: {}", model.name(), model.description());
+    }
+
+    println!("Keyboard options");
+    for group in layouts.option_groups() {
+        println!("  {}: {}", group.name(), group.description());
+        for option in group.options() {
+            println!("    {}: {}", option.name(), option.description())
+        }
+    }
+
+    println!("Keyboard layouts");
     let mut count = 0;
     for layout in layouts.layouts() {
         println!("  {}: {}", layout.name(), layout.description());
@@ -6,14 +6,24 @@ use serde_xml_rs as xml;
 use std::fs::File;
 use std::io::{self, BufReader};
 
-const X11_BASE_RULES: &str = "/usr/share/X11/xkb/rules/base.xml";
-const X11_EXTRAS_RULES: &str = "/usr/share/X11/xkb/rules/base.extras.xml";
+#[cfg(feature = "xkbregistry")]
+mod xkbregistry;
 
-/// A list of keyboard layouts parsed from `/usr/share/X11/xkb/rules/base.xml`.
+#[cfg(feature = "xkbregistry")]
+pub use crate::xkbregistry::keyboard_layouts_from_registry;
+
+const X11_DEFAULT_RULES_DIR: &str = "/usr/share/X11/xkb/rules";
+
+/// The full RMLVO (models, layouts, variants, options) content of an XKB rules file, such as
+/// `/usr/share/X11/xkb/rules/base.xml` or any other ruleset resolved through the search path.
 #[derive(Debug, Deserialize, Clone)]
 pub struct KeyboardLayouts {
+    #[serde(rename = "modelList")]
+    pub model_list:  ModelList,
     #[serde(rename = "layoutList")]
     pub layout_list: LayoutList,
+    #[serde(rename = "optionList")]
+    pub option_list: OptionList,
 }
 
 impl KeyboardLayouts {
@@ -22,6 +32,60 @@ impl KeyboardLayouts {
 
     /// Fetch the layouts from the layout list.
     pub fn layouts_mut(&mut self) -> &mut [KeyboardLayout] { &mut self.layout_list.layout }
+
+    /// Fetch the models from the model list.
+    pub fn models(&self) -> &[KeyboardModel] { &self.model_list.model }
+
+    /// Fetch the option groups from the option list.
+    pub fn option_groups(&self) -> &[OptionGroup] { &self.option_list.group }
+
+    /// Resolves a layout name and an optional variant name into references to the matching
+    /// `KeyboardLayout` and `KeyboardVariant`, returning `None` if either cannot be found.
+    pub fn resolve(
+        &self,
+        layout: &str,
+        variant: Option<&str>,
+    ) -> Option<(&KeyboardLayout, Option<&KeyboardVariant>)> {
+        let layout = self.layouts().iter().find(|l| l.name() == layout)?;
+
+        match variant {
+            Some(variant) => {
+                let variant = layout.variants()?.iter().find(|v| v.name() == variant)?;
+                Some((layout, Some(variant)))
+            }
+            None => Some((layout, None)),
+        }
+    }
+
+    /// Resolves a GNOME/systemd-style input source id, which is either a bare layout name
+    /// such as `"us"` or a combined `"layout+variant"` string such as `"us+dvorak"`, into
+    /// references to the matching `KeyboardLayout` and `KeyboardVariant`.
+    pub fn resolve_str(&self, id: &str) -> Option<(&KeyboardLayout, Option<&KeyboardVariant>)> {
+        match id.split_once('+') {
+            Some((layout, variant)) => self.resolve(layout, Some(variant)),
+            None => self.resolve(id, None),
+        }
+    }
+
+    /// Fetches the layouts whose `languageList` contains the given ISO 639 language code.
+    pub fn layouts_for_language<'a>(
+        &'a self,
+        language: &'a str,
+    ) -> impl Iterator<Item = &'a KeyboardLayout> {
+        self.layouts()
+            .iter()
+            .filter(move |layout| layout.languages().is_some_and(|languages| languages.iter().any(|l| l == language)))
+    }
+
+    /// Fetches the layouts whose `countryList` contains the given ISO 3166 country code.
+    pub fn layouts_for_country<'a>(
+        &'a self,
+        country: &'a str,
+    ) -> impl Iterator<Item = &'a KeyboardLayout> {
+        self.layouts()
+            .iter()
+            .filter(move |layout| layout.countries().is_some_and(|countries| countries.iter().any(|c| c == country)))
+    }
 }
 
 /// A list of keyboard layouts.
@@ -50,6 +114,12 @@ impl KeyboardLayout {
     pub fn variants(&self) -> Option<&Vec<KeyboardVariant>> {
         self.variant_list.as_ref().and_then(|x| x.variant.as_ref())
     }
+
+    /// Fetches the ISO 639 language codes this layout is associated with.
+    pub fn languages(&self) -> Option<&Vec<String>> { self.config_item.languages.as_ref() }
+
+    /// Fetches the ISO 3166 country codes this layout is associated with.
+    pub fn countries(&self) -> Option<&Vec<String>> { self.config_item.countries.as_ref() }
 }
 
 /// Contains the name and description of a keyboard layout.
@@ -59,6 +129,99 @@ pub struct ConfigItem {
     #[serde(rename = "shortDescription")]
     pub short_description: Option<String>,
     pub description:       String,
+    #[serde(rename = "languageList", default, deserialize_with = "deserialize_language_list")]
+    pub languages:         Option<Vec<String>>,
+    #[serde(rename = "countryList", default, deserialize_with = "deserialize_country_list")]
+    pub countries:         Option<Vec<String>>,
+}
+
+fn deserialize_language_list<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where D: serde::Deserializer<'de> {
+    #[derive(Deserialize)]
+    struct LanguageList {
+        #[serde(rename = "iso639Id", default)]
+        iso639_id: Vec<String>,
+    }
+
+    let list: Option<LanguageList> = Option::deserialize(deserializer)?;
+    Ok(list.map(|list| list.iso639_id))
+}
+
+fn deserialize_country_list<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where D: serde::Deserializer<'de> {
+    #[derive(Deserialize)]
+    struct CountryList {
+        #[serde(rename = "iso3166Id", default)]
+        iso3166_id: Vec<String>,
+    }
+
+    let list: Option<CountryList> = Option::deserialize(deserializer)?;
+    Ok(list.map(|list| list.iso3166_id))
+}
+
+/// A list of keyboard models.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ModelList {
+    #[serde(default)]
+    pub model: Vec<KeyboardModel>,
+}
+
+/// A keyboard model, such as `pc105` or `thinkpad60`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct KeyboardModel {
+    #[serde(rename = "configItem")]
+    pub config_item: ConfigItem,
+}
+
+impl KeyboardModel {
+    /// Fetches the name of the keyboard model.
+    pub fn name(&self) -> &str { &self.config_item.name }
+
+    /// Fetches a description of the keyboard model.
+    pub fn description(&self) -> &str { &self.config_item.description }
+}
+
+/// A list of option groups, such as `grp` (layout switching) or `caps` (Caps Lock behavior).
+#[derive(Debug, Deserialize, Clone)]
+pub struct OptionList {
+    #[serde(rename = "group", default)]
+    pub group: Vec<OptionGroup>,
+}
+
+/// A group of related keyboard options, only one of which may be selected unless the group
+/// allows multiple selections.
+#[derive(Debug, Deserialize, Clone)]
+pub struct OptionGroup {
+    #[serde(rename = "configItem")]
+    pub config_item: ConfigItem,
+    #[serde(rename = "option")]
+    pub option:      Vec<KeyboardOption>,
+}
+
+impl OptionGroup {
+    /// Fetches the name of the option group.
+    pub fn name(&self) -> &str { &self.config_item.name }
+
+    /// Fetches a description of the option group.
+    pub fn description(&self) -> &str { &self.config_item.description }
+
+    /// Fetches the options belonging to this group.
+    pub fn options(&self) -> &[KeyboardOption] { &self.option }
+}
+
+/// A single selectable keyboard option, such as `caps:escape`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct KeyboardOption {
+    #[serde(rename = "configItem")]
+    pub config_item: ConfigItem,
+}
+
+impl KeyboardOption {
+    /// Fetches the name of the keyboard option.
+    pub fn name(&self) -> &str { &self.config_item.name }
+
+    /// Fetches a description of the keyboard option.
+    pub fn description(&self) -> &str { &self.config_item.description }
 }
 
 /// A list of possible variants of a keyboard layout.
@@ -80,6 +243,12 @@ impl KeyboardVariant {
 
     /// A description of this variant of a keyboard layout.
     pub fn description(&self) -> &str { &self.config_item.description }
+
+    /// Fetches the ISO 639 language codes this variant is associated with.
+    pub fn languages(&self) -> Option<&Vec<String>> { self.config_item.languages.as_ref() }
+
+    /// Fetches the ISO 3166 country codes this variant is associated with.
+    pub fn countries(&self) -> Option<&Vec<String>> { self.config_item.countries.as_ref() }
 }
 
 /// Fetches a list of keyboard layouts from a path.
@@ -88,49 +257,519 @@ pub fn get_keyboard_layouts(path: &str) -> io::Result<KeyboardLayouts> {
         .map_err(|why| io::Error::new(io::ErrorKind::InvalidData, format!("{}", why)))
 }
 
-/// Fetches a list of keyboard layouts from `/usr/share/X11/xkb/rules/base.xml` or the file defined in the X11_BASE_RULES_XML environment variable.
+/// Returns the ordered list of directories to search for XKB rules files, honoring
+/// `$XKB_CONFIG_ROOT` and the XDG data dirs before falling back to the canonical
+/// `/usr/share/X11/xkb/rules` location.
+fn xkb_rules_search_path() -> Vec<String> {
+    let mut search_path = vec![];
+
+    if let Ok(xkb_config_root) = std::env::var("XKB_CONFIG_ROOT") {
+        search_path.push(format!("{}/rules", xkb_config_root));
+    }
+
+    if let Ok(xdg_data_home) = std::env::var("XDG_DATA_HOME") {
+        search_path.push(format!("{}/X11/xkb/rules", xdg_data_home));
+    }
+
+    if let Ok(xdg_data_dirs) = std::env::var("XDG_DATA_DIRS") {
+        for data_dir in xdg_data_dirs.split(':').filter(|dir| !dir.is_empty()) {
+            search_path.push(format!("{}/X11/xkb/rules", data_dir));
+        }
+    }
+
+    search_path.push(String::from(X11_DEFAULT_RULES_DIR));
+
+    search_path
+}
+
+/// Fetches a list of keyboard layouts for the named rules set (e.g. `"evdev"` or `"base"`),
+/// trying each directory in the XKB rules search path in order and returning the layouts
+/// parsed from the first `rules/{name}.xml` that opens and parses; a file that exists but
+/// fails to parse is skipped. If none does, the error of the last candidate is returned.
+pub fn get_keyboard_layouts_for_ruleset(name: &str) -> io::Result<KeyboardLayouts> {
+    let mut last_error = None;
+
+    for rules_dir in xkb_rules_search_path() {
+        match get_keyboard_layouts(&format!("{}/{}.xml", rules_dir, name)) {
+            Ok(layouts) => return Ok(layouts),
+            Err(why) => last_error = Some(why),
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("no rules file found for ruleset `{}`", name))
+    }))
+}
+
+/// Fetches a list of keyboard layouts for the `"base"` ruleset from the file defined in the
+/// `X11_BASE_RULES_XML` environment variable, or otherwise the first `rules/base.xml` found
+/// via the [`xkb_rules_search_path`] fallback chain.
 pub fn keyboard_layouts() -> io::Result<KeyboardLayouts> {
     if let Ok(x11_base_rules_xml) = std::env::var("X11_BASE_RULES_XML") {
         get_keyboard_layouts(&x11_base_rules_xml)
     }
     else {
-        get_keyboard_layouts(X11_BASE_RULES)
+        get_keyboard_layouts_for_ruleset("base")
     }
 }
 
-/// Fetches a list of keyboard layouts from `/usr/share/X11/xkb/rules/base.extras.xml` or the file defined in the X11_EXTRA_RULES_XML environment variable.
+/// Fetches a list of keyboard layouts for the `"base.extras"` ruleset from the file defined in
+/// the `X11_EXTRA_RULES_XML` environment variable, or otherwise the first `rules/base.extras.xml`
+/// found via the [`xkb_rules_search_path`] fallback chain.
 pub fn extra_keyboard_layouts() -> io::Result<KeyboardLayouts> {
     if let Ok(x11_extra_rules_xml) = std::env::var("X11_EXTRA_RULES_XML") {
         get_keyboard_layouts(&x11_extra_rules_xml)
     }
     else {
-        get_keyboard_layouts(X11_EXTRAS_RULES)
+        get_keyboard_layouts_for_ruleset("base.extras")
     }
 }
 
-/// Fetches a list of keyboard layouts from `/usr/share/X11/xkb/rules/base.xml` and
-/// extends them with the list of keyboard layouts from `/usr/share/X11/xkb/rules/base.extras.xml`.
+/// Fetches the `"base"` and `"base.extras"` rulesets and merges them with [`merge_layouts`],
+/// deduping layouts, models, and option groups by name and unioning variant lists rather than
+/// concatenating the two sources.
 pub fn all_keyboard_layouts() -> io::Result<KeyboardLayouts> {
     let base_rules = keyboard_layouts();
     let extras_rules = extra_keyboard_layouts();
 
     match (base_rules, extras_rules,) {
-        (Ok(base_rules), Ok(extras_rules)) => return Ok(merge_rules(base_rules, extras_rules)),
+        (Ok(base_rules), Ok(extras_rules)) => return Ok(merge_layouts(vec![base_rules, extras_rules])),
         (Err(why), _) => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}", why))),
         (_, Err(why)) => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}", why))),
     }
 }
 
-fn merge_rules(base: KeyboardLayouts, extras: KeyboardLayouts) -> KeyboardLayouts {
+/// Merges any number of `KeyboardLayouts` sources (e.g. base, extras, and a custom user rules
+/// file) keyed on each entry's `config_item.name`. The first source a name is seen in wins its
+/// position, so base ordering is preserved and extras-only entries are appended at the end.
+/// When a layout appears in more than one source, its variant lists are unioned, deduping
+/// variants by their own `config_item.name`.
+pub fn merge_layouts(sources: Vec<KeyboardLayouts>) -> KeyboardLayouts {
+    let mut models: Vec<KeyboardModel> = vec![];
+    let mut layouts: Vec<KeyboardLayout> = vec![];
+    let mut groups: Vec<OptionGroup> = vec![];
+
+    for source in sources {
+        for model in source.model_list.model {
+            if !models.iter().any(|existing| existing.name() == model.name()) {
+                models.push(model);
+            }
+        }
+
+        for layout in source.layout_list.layout {
+            match layouts.iter_mut().find(|existing| existing.name() == layout.name()) {
+                Some(existing) => merge_variants(existing, layout.variant_list),
+                None => layouts.push(layout),
+            }
+        }
+
+        for group in source.option_list.group {
+            if !groups.iter().any(|existing| existing.name() == group.name()) {
+                groups.push(group);
+            }
+        }
+    }
+
     KeyboardLayouts {
-        layout_list: concat_layout_lists(vec![base.layout_list, extras.layout_list])
+        model_list:  ModelList { model: models },
+        layout_list: LayoutList { layout: layouts },
+        option_list: OptionList { group: groups },
+    }
+}
+
+/// Unions `extra_variants` into `layout`'s variant list, deduping by `config_item.name` and
+/// preserving the order variants were first seen in.
+fn merge_variants(layout: &mut KeyboardLayout, extra_variants: Option<VariantList>) {
+    let extra_variants = match extra_variants.and_then(|list| list.variant) {
+        Some(variants) => variants,
+        None => return,
+    };
+
+    let variants = layout
+        .variant_list
+        .get_or_insert(VariantList { variant: None })
+        .variant
+        .get_or_insert_with(Vec::new);
+
+    for variant in extra_variants {
+        if !variants.iter().any(|existing| existing.name() == variant.name()) {
+            variants.push(variant);
+        }
     }
 }
 
-fn concat_layout_lists(layouts: Vec<LayoutList>) -> LayoutList {
-    let mut new_layouts = vec![];
-    for layout_list in layouts.into_iter() {
-        new_layouts.extend(layout_list.layout);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // `xkb_rules_search_path` reads process-wide environment variables, so tests that set
+    // them are serialized against each other to avoid racing on shared state.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    fn clear_search_path_env() {
+        std::env::remove_var("XKB_CONFIG_ROOT");
+        std::env::remove_var("XDG_DATA_HOME");
+        std::env::remove_var("XDG_DATA_DIRS");
+    }
+
+    #[test]
+    fn search_path_defaults_to_x11_dir_when_no_env_vars_are_set() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        clear_search_path_env();
+
+        assert_eq!(xkb_rules_search_path(), vec![X11_DEFAULT_RULES_DIR.to_owned()]);
+    }
+
+    #[test]
+    fn search_path_orders_xkb_config_root_before_xdg_dirs_before_default() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        clear_search_path_env();
+        std::env::set_var("XKB_CONFIG_ROOT", "/xkb-root");
+        std::env::set_var("XDG_DATA_HOME", "/xdg-home");
+        std::env::set_var("XDG_DATA_DIRS", "/xdg-dir-a:/xdg-dir-b");
+
+        assert_eq!(xkb_rules_search_path(), vec![
+            "/xkb-root/rules".to_owned(),
+            "/xdg-home/X11/xkb/rules".to_owned(),
+            "/xdg-dir-a/X11/xkb/rules".to_owned(),
+            "/xdg-dir-b/X11/xkb/rules".to_owned(),
+            X11_DEFAULT_RULES_DIR.to_owned(),
+        ]);
+
+        clear_search_path_env();
+    }
+
+    #[test]
+    fn search_path_filters_empty_xdg_data_dirs_segments() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        clear_search_path_env();
+        std::env::set_var("XDG_DATA_DIRS", "/xdg-dir-a::/xdg-dir-b:");
+
+        assert_eq!(xkb_rules_search_path(), vec![
+            "/xdg-dir-a/X11/xkb/rules".to_owned(),
+            "/xdg-dir-b/X11/xkb/rules".to_owned(),
+            X11_DEFAULT_RULES_DIR.to_owned(),
+        ]);
+
+        clear_search_path_env();
+    }
+
+    #[test]
+    fn get_keyboard_layouts_for_ruleset_falls_through_search_path() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        clear_search_path_env();
+
+        let test_root =
+            std::env::temp_dir().join(format!("xkb-data-test-{}-ruleset-fallback", std::process::id()));
+        let xkb_config_root = test_root.join("missing");
+        let xdg_data_home = test_root.join("present");
+        let rules_dir = xdg_data_home.join("X11/xkb/rules");
+        std::fs::create_dir_all(&rules_dir).unwrap();
+        std::fs::write(
+            rules_dir.join("custom.xml"),
+            r#"<xkbConfigRegistry>
+                <modelList/>
+                <layoutList>
+                    <layout>
+                        <configItem>
+                            <name>us</name>
+                            <description>English (US)</description>
+                        </configItem>
+                    </layout>
+                </layoutList>
+                <optionList/>
+            </xkbConfigRegistry>"#,
+        )
+        .unwrap();
+
+        // `xkb_config_root` is never created, so the first search path entry it produces
+        // fails to open and the search falls through to `xdg_data_home`'s entry.
+        std::env::set_var("XKB_CONFIG_ROOT", &xkb_config_root);
+        std::env::set_var("XDG_DATA_HOME", &xdg_data_home);
+
+        let layouts = get_keyboard_layouts_for_ruleset("custom").unwrap();
+        assert_eq!(layouts.layouts()[0].name(), "us");
+
+        clear_search_path_env();
+        std::fs::remove_dir_all(&test_root).unwrap();
+    }
+
+    fn config_item(name: &str) -> ConfigItem {
+        ConfigItem {
+            name: name.to_owned(),
+            short_description: None,
+            description: name.to_owned(),
+            languages: None,
+            countries: None,
+        }
+    }
+
+    #[test]
+    fn model_list_and_option_list_default_on_self_closing_element() {
+        let xml = r#"<xkbConfigRegistry>
+            <modelList/>
+            <layoutList>
+                <layout>
+                    <configItem>
+                        <name>us</name>
+                        <description>English (US)</description>
+                    </configItem>
+                </layout>
+            </layoutList>
+            <optionList/>
+        </xkbConfigRegistry>"#;
+
+        let layouts: KeyboardLayouts = xml::from_str(xml).unwrap();
+        assert!(layouts.models().is_empty());
+        assert!(layouts.option_groups().is_empty());
+        assert_eq!(layouts.layouts()[0].name(), "us");
+    }
+
+    #[test]
+    fn model_list_and_option_list_parse_populated_entries() {
+        let xml = r#"<xkbConfigRegistry>
+            <modelList>
+                <model>
+                    <configItem>
+                        <name>pc105</name>
+                        <description>Generic 105-key PC</description>
+                    </configItem>
+                </model>
+            </modelList>
+            <layoutList>
+                <layout>
+                    <configItem>
+                        <name>us</name>
+                        <description>English (US)</description>
+                    </configItem>
+                </layout>
+            </layoutList>
+            <optionList>
+                <group>
+                    <configItem>
+                        <name>grp</name>
+                        <description>Switching to another layout</description>
+                    </configItem>
+                    <option>
+                        <configItem>
+                            <name>grp:alt_shift_toggle</name>
+                            <description>Alt+Shift</description>
+                        </configItem>
+                    </option>
+                </group>
+            </optionList>
+        </xkbConfigRegistry>"#;
+
+        let layouts: KeyboardLayouts = xml::from_str(xml).unwrap();
+        assert_eq!(layouts.models()[0].name(), "pc105");
+
+        let group = &layouts.option_groups()[0];
+        assert_eq!(group.name(), "grp");
+        assert_eq!(group.options()[0].name(), "grp:alt_shift_toggle");
+    }
+
+    #[test]
+    fn language_list_and_country_list_default_to_none_when_absent() {
+        let xml = r#"<configItem>
+            <name>us</name>
+            <description>English (US)</description>
+        </configItem>"#;
+
+        let config_item: ConfigItem = xml::from_str(xml).unwrap();
+        assert!(config_item.languages.is_none());
+        assert!(config_item.countries.is_none());
+    }
+
+    #[test]
+    fn language_list_and_country_list_parse_populated_entries() {
+        let xml = r#"<configItem>
+            <name>us</name>
+            <description>English (US)</description>
+            <languageList>
+                <iso639Id>eng</iso639Id>
+            </languageList>
+            <countryList>
+                <iso3166Id>US</iso3166Id>
+            </countryList>
+        </configItem>"#;
+
+        let config_item: ConfigItem = xml::from_str(xml).unwrap();
+        assert_eq!(config_item.languages.unwrap(), vec!["eng".to_owned()]);
+        assert_eq!(config_item.countries.unwrap(), vec!["US".to_owned()]);
+    }
+
+    fn layout_with_locale(name: &str, languages: &[&str], countries: &[&str]) -> KeyboardLayout {
+        KeyboardLayout {
+            config_item:  ConfigItem {
+                name: name.to_owned(),
+                short_description: None,
+                description: name.to_owned(),
+                languages: Some(languages.iter().map(|l| l.to_string()).collect()),
+                countries: Some(countries.iter().map(|c| c.to_string()).collect()),
+            },
+            variant_list: None,
+        }
+    }
+
+    #[test]
+    fn layouts_for_language_filters_by_iso639_code() {
+        let layouts = KeyboardLayouts {
+            model_list:  ModelList { model: vec![] },
+            layout_list: LayoutList {
+                layout: vec![layout_with_locale("us", &["eng"], &["US"]), layout_with_locale("de", &["deu"], &["DE"])],
+            },
+            option_list: OptionList { group: vec![] },
+        };
+
+        let matches: Vec<&str> = layouts.layouts_for_language("eng").map(|l| l.name()).collect();
+        assert_eq!(matches, vec!["us"]);
+        assert!(layouts.layouts_for_language("fra").next().is_none());
+    }
+
+    #[test]
+    fn layouts_for_country_filters_by_iso3166_code() {
+        let layouts = KeyboardLayouts {
+            model_list:  ModelList { model: vec![] },
+            layout_list: LayoutList {
+                layout: vec![layout_with_locale("us", &["eng"], &["US"]), layout_with_locale("de", &["deu"], &["DE"])],
+            },
+            option_list: OptionList { group: vec![] },
+        };
+
+        let matches: Vec<&str> = layouts.layouts_for_country("DE").map(|l| l.name()).collect();
+        assert_eq!(matches, vec!["de"]);
+        assert!(layouts.layouts_for_country("FR").next().is_none());
+    }
+
+    fn layouts_fixture() -> KeyboardLayouts {
+        KeyboardLayouts {
+            model_list:  ModelList { model: vec![] },
+            layout_list: LayoutList {
+                layout: vec![
+                    KeyboardLayout {
+                        config_item:  config_item("us"),
+                        variant_list: Some(VariantList {
+                            variant: Some(vec![KeyboardVariant { config_item: config_item("dvorak") }]),
+                        }),
+                    },
+                    KeyboardLayout { config_item: config_item("de"), variant_list: None },
+                ],
+            },
+            option_list: OptionList { group: vec![] },
+        }
+    }
+
+    #[test]
+    fn resolve_finds_bare_layout() {
+        let layouts = layouts_fixture();
+        let (layout, variant) = layouts.resolve("de", None).unwrap();
+        assert_eq!(layout.name(), "de");
+        assert!(variant.is_none());
+    }
+
+    #[test]
+    fn resolve_finds_layout_and_variant() {
+        let layouts = layouts_fixture();
+        let (layout, variant) = layouts.resolve("us", Some("dvorak")).unwrap();
+        assert_eq!(layout.name(), "us");
+        assert_eq!(variant.unwrap().name(), "dvorak");
+    }
+
+    #[test]
+    fn resolve_rejects_unknown_layout_or_variant() {
+        let layouts = layouts_fixture();
+        assert!(layouts.resolve("xx", None).is_none());
+        assert!(layouts.resolve("us", Some("xx")).is_none());
+        assert!(layouts.resolve("de", Some("dvorak")).is_none());
+    }
+
+    #[test]
+    fn resolve_str_splits_on_plus() {
+        let layouts = layouts_fixture();
+        let (layout, variant) = layouts.resolve_str("us+dvorak").unwrap();
+        assert_eq!(layout.name(), "us");
+        assert_eq!(variant.unwrap().name(), "dvorak");
+    }
+
+    #[test]
+    fn resolve_str_without_plus_is_bare_layout() {
+        let layouts = layouts_fixture();
+        let (layout, variant) = layouts.resolve_str("de").unwrap();
+        assert_eq!(layout.name(), "de");
+        assert!(variant.is_none());
+    }
+
+    fn layout(name: &str, variants: &[&str]) -> KeyboardLayout {
+        KeyboardLayout {
+            config_item:  config_item(name),
+            variant_list: if variants.is_empty() {
+                None
+            } else {
+                Some(VariantList {
+                    variant: Some(variants.iter().map(|v| KeyboardVariant { config_item: config_item(v) }).collect()),
+                })
+            },
+        }
+    }
+
+    #[test]
+    fn merge_layouts_preserves_base_order_and_appends_extras_only_entries() {
+        let base = KeyboardLayouts {
+            model_list:  ModelList { model: vec![] },
+            layout_list: LayoutList { layout: vec![layout("us", &[]), layout("de", &[])] },
+            option_list: OptionList { group: vec![] },
+        };
+        let extras = KeyboardLayouts {
+            model_list:  ModelList { model: vec![] },
+            layout_list: LayoutList { layout: vec![layout("fr", &[]), layout("us", &[])] },
+            option_list: OptionList { group: vec![] },
+        };
+
+        let merged = merge_layouts(vec![base, extras]);
+        let names: Vec<&str> = merged.layouts().iter().map(|l| l.name()).collect();
+        assert_eq!(names, vec!["us", "de", "fr"]);
+    }
+
+    #[test]
+    fn merge_layouts_unions_and_dedups_variants() {
+        let base = KeyboardLayouts {
+            model_list:  ModelList { model: vec![] },
+            layout_list: LayoutList { layout: vec![layout("us", &["dvorak"])] },
+            option_list: OptionList { group: vec![] },
+        };
+        let extras = KeyboardLayouts {
+            model_list:  ModelList { model: vec![] },
+            layout_list: LayoutList { layout: vec![layout("us", &["dvorak", "colemak"])] },
+            option_list: OptionList { group: vec![] },
+        };
+
+        let merged = merge_layouts(vec![base, extras]);
+        let us = merged.resolve("us", None).unwrap().0;
+        let variant_names: Vec<&str> = us.variants().unwrap().iter().map(|v| v.name()).collect();
+        assert_eq!(variant_names, vec!["dvorak", "colemak"]);
+    }
+
+    #[test]
+    fn merge_layouts_dedups_models_and_option_groups_by_name() {
+        let model = |name: &str| KeyboardModel { config_item: config_item(name) };
+        let group = |name: &str| OptionGroup { config_item: config_item(name), option: vec![] };
+
+        let base = KeyboardLayouts {
+            model_list:  ModelList { model: vec![model("pc105")] },
+            layout_list: LayoutList { layout: vec![] },
+            option_list: OptionList { group: vec![group("grp")] },
+        };
+        let extras = KeyboardLayouts {
+            model_list:  ModelList { model: vec![model("pc105"), model("thinkpad60")] },
+            layout_list: LayoutList { layout: vec![] },
+            option_list: OptionList { group: vec![group("grp"), group("caps")] },
+        };
+
+        let merged = merge_layouts(vec![base, extras]);
+        let model_names: Vec<&str> = merged.models().iter().map(|m| m.name()).collect();
+        let group_names: Vec<&str> = merged.option_groups().iter().map(|g| g.name()).collect();
+        assert_eq!(model_names, vec!["pc105", "thinkpad60"]);
+        assert_eq!(group_names, vec!["grp", "caps"]);
     }
-    return LayoutList { layout: new_layouts }
 }
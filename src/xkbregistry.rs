@@ -0,0 +1,211 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! An alternative backend that populates `KeyboardLayouts` via `libxkbregistry` instead of
+//! parsing the rules XML by hand. This is enabled with the `xkbregistry` cargo feature and
+//! returns the same `KeyboardLayouts` type as the XML backend, so callers can stay
+//! backend-agnostic.
+//!
+//! The `xkbregistry` crate only exposes the raw `rxkb_*` C API generated by `bindgen`, so
+//! this module talks to `libxkbregistry` directly through FFI and owns the lifetime of the
+//! `rxkb_context` it creates.
+
+use crate::{
+    ConfigItem, KeyboardLayout, KeyboardModel, KeyboardOption, KeyboardLayouts, KeyboardVariant,
+    LayoutList, ModelList, OptionGroup, OptionList, VariantList,
+};
+use std::ffi::CStr;
+use std::io;
+use std::os::raw::c_char;
+use xkbregistry::{
+    rxkb_context,
+    rxkb_context_flags_RXKB_CONTEXT_LOAD_EXOTIC_LAYOUTS as RXKB_CONTEXT_LOAD_EXOTIC_LAYOUTS,
+    rxkb_context_include_path_append_default, rxkb_context_new,
+    rxkb_context_parse_default_ruleset, rxkb_context_unref, rxkb_iso3166_code_get_code,
+    rxkb_iso3166_next, rxkb_iso639_code_get_code, rxkb_iso639_next, rxkb_layout, rxkb_layout_first,
+    rxkb_layout_get_description, rxkb_layout_get_iso3166_first, rxkb_layout_get_iso639_first,
+    rxkb_layout_get_name, rxkb_layout_get_variant, rxkb_layout_next, rxkb_model, rxkb_model_first,
+    rxkb_model_get_description, rxkb_model_get_name, rxkb_model_next, rxkb_option_first,
+    rxkb_option_get_description, rxkb_option_get_name, rxkb_option_group, rxkb_option_group_first,
+    rxkb_option_group_get_description, rxkb_option_group_get_name, rxkb_option_group_next,
+    rxkb_option_next,
+};
+
+/// Fetches a list of keyboard layouts, models, and options from `libxkbregistry`, which
+/// already merges the base and extras rules (and any custom user rule directories) using
+/// the correct include/merge semantics.
+pub fn keyboard_layouts_from_registry() -> io::Result<KeyboardLayouts> {
+    // SAFETY: `rxkb_context_new` either returns a valid, owned context or null on allocation
+    // failure; the null case is checked below before the pointer is used further.
+    // `RXKB_CONTEXT_LOAD_EXOTIC_LAYOUTS` makes the registry parse the `*.extras.xml` rules
+    // alongside the base rules.
+    let context = unsafe { rxkb_context_new(RXKB_CONTEXT_LOAD_EXOTIC_LAYOUTS) };
+
+    if context.is_null() {
+        return Err(io::Error::other("failed to create an rxkb_context"));
+    }
+
+    // SAFETY: `context` was just checked to be non-null and is owned by this function until
+    // `rxkb_context_unref` is called below.
+    let result = unsafe { parse_and_convert(context) };
+
+    // SAFETY: `context` is non-null and not used again after this call.
+    unsafe { rxkb_context_unref(context) };
+
+    result
+}
+
+unsafe fn parse_and_convert(context: *mut rxkb_context) -> io::Result<KeyboardLayouts> {
+    if !rxkb_context_include_path_append_default(context) {
+        return Err(io::Error::other("failed to add the default include paths"));
+    }
+
+    if !rxkb_context_parse_default_ruleset(context) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "failed to parse the default rules"));
+    }
+
+    let mut model = Vec::new();
+    let mut m = rxkb_model_first(context);
+    while !m.is_null() {
+        model.push(convert_model(m));
+        m = rxkb_model_next(m);
+    }
+
+    let mut layout: Vec<KeyboardLayout> = Vec::new();
+    let mut l = rxkb_layout_first(context);
+    while !l.is_null() {
+        push_layout(&mut layout, l);
+        l = rxkb_layout_next(l);
+    }
+
+    let mut group = Vec::new();
+    let mut g = rxkb_option_group_first(context);
+    while !g.is_null() {
+        group.push(convert_option_group(g));
+        g = rxkb_option_group_next(g);
+    }
+
+    Ok(KeyboardLayouts {
+        model_list:  ModelList { model },
+        layout_list: LayoutList { layout },
+        option_list: OptionList { group },
+    })
+}
+
+/// Converts a single `rxkb_layout` entry and folds it into `layouts`. The registry reports
+/// each layout/variant pair as its own flat entry (a base layout has `rxkb_layout_get_variant()
+/// == NULL`, a variant does not), so variants are merged into the `KeyboardLayout` for their
+/// base layout name rather than pushed as separate top-level layouts.
+unsafe fn push_layout(layouts: &mut Vec<KeyboardLayout>, l: *mut rxkb_layout) {
+    let name = cstr(rxkb_layout_get_name(l));
+    let description = cstr(rxkb_layout_get_description(l));
+    let languages = layout_languages(l);
+    let countries = layout_countries(l);
+    let variant_name = rxkb_layout_get_variant(l);
+
+    if variant_name.is_null() {
+        let config_item = convert_config_item_with_locale(name.clone(), description, languages, countries);
+        match layouts.iter_mut().find(|existing| existing.name() == name) {
+            // A variant was already seen for this layout name before its base entry (that
+            // earlier placeholder only had the layout name as its description), or the layout
+            // is listed again by the extras rules; either way the `configItem` of this latest
+            // base entry replaces the earlier one.
+            Some(existing) => existing.config_item = config_item,
+            None => layouts.push(KeyboardLayout { config_item, variant_list: None }),
+        }
+        return;
+    }
+
+    let variant = KeyboardVariant {
+        config_item: convert_config_item_with_locale(cstr(variant_name), description, languages, countries),
+    };
+
+    match layouts.iter_mut().find(|existing| existing.name() == name) {
+        Some(existing) => existing
+            .variant_list
+            .get_or_insert(VariantList { variant: None })
+            .variant
+            .get_or_insert_with(Vec::new)
+            .push(variant),
+        None => layouts.push(KeyboardLayout {
+            config_item:  convert_config_item(name.clone(), name),
+            variant_list: Some(VariantList { variant: Some(vec![variant]) }),
+        }),
+    }
+}
+
+/// Walks the `rxkb_iso639_code` list attached to a layout or variant, collecting its ISO 639
+/// language codes, the same way `convert_option_group` walks a group's options.
+unsafe fn layout_languages(l: *mut rxkb_layout) -> Option<Vec<String>> {
+    let mut languages = Vec::new();
+    let mut code = rxkb_layout_get_iso639_first(l);
+    while !code.is_null() {
+        languages.push(cstr(rxkb_iso639_code_get_code(code)));
+        code = rxkb_iso639_next(code);
+    }
+
+    if languages.is_empty() { None } else { Some(languages) }
+}
+
+/// Walks the `rxkb_iso3166_code` list attached to a layout or variant, collecting its ISO 3166
+/// country codes, the same way `convert_option_group` walks a group's options.
+unsafe fn layout_countries(l: *mut rxkb_layout) -> Option<Vec<String>> {
+    let mut countries = Vec::new();
+    let mut code = rxkb_layout_get_iso3166_first(l);
+    while !code.is_null() {
+        countries.push(cstr(rxkb_iso3166_code_get_code(code)));
+        code = rxkb_iso3166_next(code);
+    }
+
+    if countries.is_empty() { None } else { Some(countries) }
+}
+
+fn convert_config_item(name: String, description: String) -> ConfigItem {
+    ConfigItem { name, short_description: None, description, languages: None, countries: None }
+}
+
+fn convert_config_item_with_locale(
+    name: String,
+    description: String,
+    languages: Option<Vec<String>>,
+    countries: Option<Vec<String>>,
+) -> ConfigItem {
+    ConfigItem { name, short_description: None, description, languages, countries }
+}
+
+unsafe fn convert_model(model: *mut rxkb_model) -> KeyboardModel {
+    KeyboardModel {
+        config_item: convert_config_item(
+            cstr(rxkb_model_get_name(model)),
+            cstr(rxkb_model_get_description(model)),
+        ),
+    }
+}
+
+unsafe fn convert_option_group(group: *mut rxkb_option_group) -> OptionGroup {
+    let mut option = Vec::new();
+    let mut o = rxkb_option_first(group);
+    while !o.is_null() {
+        option.push(KeyboardOption {
+            config_item: convert_config_item(
+                cstr(rxkb_option_get_name(o)),
+                cstr(rxkb_option_get_description(o)),
+            ),
+        });
+        o = rxkb_option_next(o);
+    }
+
+    OptionGroup {
+        config_item: convert_config_item(
+            cstr(rxkb_option_group_get_name(group)),
+            cstr(rxkb_option_group_get_description(group)),
+        ),
+        option,
+    }
+}
+
+/// Copies a C string owned by `libxkbregistry` into a Rust `String`, treating a null pointer
+/// (which the registry uses for absent optional fields) as empty.
+unsafe fn cstr(ptr: *const c_char) -> String {
+    if ptr.is_null() { String::new() } else { CStr::from_ptr(ptr).to_string_lossy().into_owned() }
+}